@@ -4,12 +4,129 @@ use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::io::Cursor;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
 // メッセージの種類
 #[derive(Serialize, Deserialize)]
 pub enum MessageType {
     Click { x: usize, y: usize },
-    ColorUpdate { entity: u32, r: u8, g: u8, b: u8 },
+    ColorUpdate { entity: u32, r: u8, g: u8, b: u8, version: u64, client_id: u32 },
+    TextUpdate { entity: u32, text: String },
+    Cursor { client_id: u32, x: usize, y: usize },
+    // 全セルのパックドRGBAバッファを一括送信する（新規参加ピアへの同期用）
+    Snapshot { pixels: Vec<u8> },
+}
+
+impl MessageType {
+    /// 1バイトのタグ (0 = Click, 1 = ColorUpdate, 2 = TextUpdate, 3 = Cursor,
+    /// 4 = Snapshot) に続けてリトルエンディアンのフィールドを並べた
+    /// バイナリ表現にエンコードする。Click/ColorUpdate/Cursorは固定長、
+    /// TextUpdateとSnapshotは可変長部分の前に `u32` の長さを置く。
+    /// JSON文字列に比べてサイズが小さく、JSONのパースも発生しない。
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        match self {
+            MessageType::Click { x, y } => {
+                buf.write_u8(0).unwrap();
+                buf.write_u32::<LittleEndian>(*x as u32).unwrap();
+                buf.write_u32::<LittleEndian>(*y as u32).unwrap();
+            }
+            MessageType::ColorUpdate { entity, r, g, b, version, client_id } => {
+                buf.write_u8(1).unwrap();
+                buf.write_u32::<LittleEndian>(*entity).unwrap();
+                buf.write_u8(*r).unwrap();
+                buf.write_u8(*g).unwrap();
+                buf.write_u8(*b).unwrap();
+                buf.write_u64::<LittleEndian>(*version).unwrap();
+                buf.write_u32::<LittleEndian>(*client_id).unwrap();
+            }
+            MessageType::TextUpdate { entity, text } => {
+                buf.write_u8(2).unwrap();
+                buf.write_u32::<LittleEndian>(*entity).unwrap();
+                let text_bytes = text.as_bytes();
+                buf.write_u32::<LittleEndian>(text_bytes.len() as u32).unwrap();
+                buf.extend_from_slice(text_bytes);
+            }
+            MessageType::Cursor { client_id, x, y } => {
+                buf.write_u8(3).unwrap();
+                buf.write_u32::<LittleEndian>(*client_id).unwrap();
+                buf.write_u32::<LittleEndian>(*x as u32).unwrap();
+                buf.write_u32::<LittleEndian>(*y as u32).unwrap();
+            }
+            MessageType::Snapshot { pixels } => {
+                buf.write_u8(4).unwrap();
+                buf.write_u32::<LittleEndian>(pixels.len() as u32).unwrap();
+                buf.extend_from_slice(pixels);
+            }
+        }
+        buf
+    }
+
+    /// `to_bytes` で作られたバイト列をデコードする。タグが未知、または
+    /// バッファが途中で切れている場合は `None` を返す。
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut cursor = Cursor::new(bytes);
+        let tag = cursor.read_u8().ok()?;
+        match tag {
+            0 => {
+                let x = cursor.read_u32::<LittleEndian>().ok()? as usize;
+                let y = cursor.read_u32::<LittleEndian>().ok()? as usize;
+                Some(MessageType::Click { x, y })
+            }
+            1 => {
+                let entity = cursor.read_u32::<LittleEndian>().ok()?;
+                let r = cursor.read_u8().ok()?;
+                let g = cursor.read_u8().ok()?;
+                let b = cursor.read_u8().ok()?;
+                let version = cursor.read_u64::<LittleEndian>().ok()?;
+                let client_id = cursor.read_u32::<LittleEndian>().ok()?;
+                Some(MessageType::ColorUpdate { entity, r, g, b, version, client_id })
+            }
+            2 => {
+                let entity = cursor.read_u32::<LittleEndian>().ok()?;
+                let len = cursor.read_u32::<LittleEndian>().ok()? as usize;
+                let start = cursor.position() as usize;
+                let end = start.checked_add(len)?;
+                let text_bytes = bytes.get(start..end)?;
+                let text = String::from_utf8(text_bytes.to_vec()).ok()?;
+                Some(MessageType::TextUpdate { entity, text })
+            }
+            3 => {
+                let client_id = cursor.read_u32::<LittleEndian>().ok()?;
+                let x = cursor.read_u32::<LittleEndian>().ok()? as usize;
+                let y = cursor.read_u32::<LittleEndian>().ok()? as usize;
+                Some(MessageType::Cursor { client_id, x, y })
+            }
+            4 => {
+                let len = cursor.read_u32::<LittleEndian>().ok()? as usize;
+                let start = cursor.position() as usize;
+                let end = start.checked_add(len)?;
+                let pixels = bytes.get(start..end)?;
+                Some(MessageType::Snapshot { pixels: pixels.to_vec() })
+            }
+            _ => None,
+        }
+    }
+
+    /// ペイロードの前に `u32` の長さを付けてエンコードする。
+    fn to_framed_bytes(&self) -> Vec<u8> {
+        let payload = self.to_bytes();
+        let mut framed = Vec::with_capacity(4 + payload.len());
+        framed.write_u32::<LittleEndian>(payload.len() as u32).unwrap();
+        framed.extend_from_slice(&payload);
+        framed
+    }
+
+    /// `to_framed_bytes` の逆変換。長さプレフィックスが読めない、または
+    /// 宣言された長さ分のデータが揃っていない場合は `None` を返す。
+    fn from_framed_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut cursor = Cursor::new(bytes);
+        let len = cursor.read_u32::<LittleEndian>().ok()? as usize;
+        let end = 4usize.checked_add(len)?;
+        let payload = bytes.get(4..end)?;
+        Self::from_bytes(payload)
+    }
 }
 
 // ECSの基本コンポーネント
@@ -32,6 +149,115 @@ pub struct Color {
     pub b: u8,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Text {
+    // Minecraft形式のセクション記号（§）エスケープを含む生文字列。
+    // 描画時に `convert_legacy` でスタイル付きランに展開される。
+    pub raw: String,
+}
+
+// §エスケープで切り替わる1つのスタイル区間
+#[derive(Debug, Clone)]
+struct TextRun {
+    text: String,
+    color: &'static str,
+    bold: bool,
+    italic: bool,
+}
+
+// レガシーカラーコード（0-9, a-f）に対応する16色パレット
+const LEGACY_COLORS: [(char, &str); 16] = [
+    ('0', "#000000"),
+    ('1', "#0000AA"),
+    ('2', "#00AA00"),
+    ('3', "#00AAAA"),
+    ('4', "#AA0000"),
+    ('5', "#AA00AA"),
+    ('6', "#FFAA00"),
+    ('7', "#AAAAAA"),
+    ('8', "#555555"),
+    ('9', "#5555FF"),
+    ('a', "#55FF55"),
+    ('b', "#55FFFF"),
+    ('c', "#FF5555"),
+    ('d', "#FF55FF"),
+    ('e', "#FFFF55"),
+    ('f', "#FFFFFF"),
+];
+
+fn legacy_color(code: char) -> Option<&'static str> {
+    LEGACY_COLORS.iter().find(|(c, _)| *c == code).map(|(_, hex)| *hex)
+}
+
+/// §に続くコード文字で色・太字・斜体を切り替えながら、生文字列をスタイル
+/// 付きのラン列に分解する。未知のコードはリテラルの`§`+文字として扱い、
+/// 末尾に取り残された単独の`§`は読み捨てる。
+fn convert_legacy(raw: &str) -> Vec<TextRun> {
+    let mut runs = Vec::new();
+    let mut current = String::new();
+    let mut color = "#FFFFFF";
+    let mut bold = false;
+    let mut italic = false;
+
+    let mut chars = raw.char_indices();
+    while let Some((_, ch)) = chars.next() {
+        if ch != '§' {
+            current.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            None => {
+                // 末尾の単独の§は捨てる
+            }
+            Some((_, code)) => {
+                if let Some(hex) = legacy_color(code) {
+                    if !current.is_empty() {
+                        runs.push(TextRun { text: std::mem::take(&mut current), color, bold, italic });
+                    }
+                    color = hex;
+                    bold = false;
+                    italic = false;
+                } else {
+                    match code {
+                        'l' => {
+                            if !current.is_empty() {
+                                runs.push(TextRun { text: std::mem::take(&mut current), color, bold, italic });
+                            }
+                            bold = true;
+                        }
+                        'o' => {
+                            if !current.is_empty() {
+                                runs.push(TextRun { text: std::mem::take(&mut current), color, bold, italic });
+                            }
+                            italic = true;
+                        }
+                        'r' => {
+                            if !current.is_empty() {
+                                runs.push(TextRun { text: std::mem::take(&mut current), color, bold, italic });
+                            }
+                            color = "#FFFFFF";
+                            bold = false;
+                            italic = false;
+                        }
+                        _ => {
+                            // 未知のコードはリテラルとして扱う
+                            current.push('§');
+                            current.push(code);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if !current.is_empty() {
+        runs.push(TextRun { text: current, color, bold, italic });
+    }
+
+    runs
+}
+
 // エンティティID
 type EntityId = u32;
 
@@ -66,6 +292,21 @@ pub struct World {
     positions: ComponentStorage<Position>,
     sizes: ComponentStorage<Size>,
     colors: ComponentStorage<Color>,
+    texts: ComponentStorage<Text>,
+    // このピアを一意に識別するID。Lamportクロックの同点判定に使う。
+    client_id: u32,
+    // ローカルのLamportクロック。更新のたびに増分する。
+    clock: u64,
+    // 各エンティティに最後に適用された (version, client_id) のペア。
+    // これより新しくない更新は破棄する（last-writer-wins）。
+    last_version: HashMap<EntityId, (u64, u32)>,
+    // 前回の描画から色またはラベルが変わったエンティティ。`render_dirty` はこの集合
+    // だけを再描画し、グリッド全体をクリア＆走査するコストを避ける。
+    dirty: Vec<EntityId>,
+    // エンティティIDごとに4バイト (RGBA) を詰めたパックドバッファ。
+    // Snapshot適用時にまとめてmemcpyでき、描画時は `colors` ハッシュマップを
+    // 引かずにここから色を読む描画用バッキングストア。
+    pixels: Vec<u8>,
 }
 
 impl World {
@@ -75,6 +316,12 @@ impl World {
             positions: ComponentStorage::new(),
             sizes: ComponentStorage::new(),
             colors: ComponentStorage::new(),
+            texts: ComponentStorage::new(),
+            client_id: (js_sys::Math::random() * u32::MAX as f64) as u32,
+            clock: 0,
+            last_version: HashMap::new(),
+            dirty: Vec::new(),
+            pixels: Vec::new(),
         }
     }
 
@@ -93,7 +340,210 @@ impl World {
     }
 
     pub fn add_color(&mut self, entity: EntityId, color: Color) {
+        self.set_color(entity, color);
+    }
+
+    /// エンティティの色を更新し、パックドピクセルバッファに反映した上で
+    /// `dirty` 集合に積む。`apply_snapshot` 以外で色を変える経路はここを通る。
+    pub fn set_color(&mut self, entity: EntityId, color: Color) {
+        let offset = entity as usize * 4;
+        if self.pixels.len() < offset + 4 {
+            self.pixels.resize(offset + 4, 0);
+        }
+        self.pixels[offset] = color.r;
+        self.pixels[offset + 1] = color.g;
+        self.pixels[offset + 2] = color.b;
+        self.pixels[offset + 3] = 255;
+
         self.colors.insert(entity, color);
+        self.dirty.push(entity);
+    }
+
+    /// パックドピクセルバッファから色を読み出す。描画パスはこちらを参照し、
+    /// `colors` ハッシュマップへのルックアップを挟まない。
+    pub fn pixel_color(&self, entity: EntityId) -> Option<Color> {
+        let offset = entity as usize * 4;
+        if self.pixels.len() < offset + 4 {
+            return None;
+        }
+        Some(Color {
+            r: self.pixels[offset],
+            g: self.pixels[offset + 1],
+            b: self.pixels[offset + 2],
+        })
+    }
+
+    /// 前回の描画からの変更を取り出し、`dirty` 集合をクリアする。
+    /// 同じエンティティが複数回積まれていてもソート＆重複排除して
+    /// 1エンティティにつき1回だけ描画されるようにする。
+    pub fn take_dirty(&mut self) -> Vec<EntityId> {
+        let mut dirty = std::mem::take(&mut self.dirty);
+        dirty.sort_unstable();
+        dirty.dedup();
+        dirty
+    }
+
+    /// パックドRGBAバッファを一括適用する（全セルの同期など）。
+    /// 受け取ったバッファをパックドピクセルバッファへそのままmemcpyする。
+    /// その後、`colors` ハッシュマップにもエンティティ1件ずつ色を書き込み、
+    /// 全エンティティを `dirty` 集合に積む。
+    pub fn apply_snapshot(&mut self, pixels: &[u8]) {
+        self.pixels.resize(pixels.len(), 0);
+        self.pixels.copy_from_slice(pixels);
+
+        for entity in 0..(pixels.len() / 4) as EntityId {
+            let offset = entity as usize * 4;
+            let color = Color {
+                r: pixels[offset],
+                g: pixels[offset + 1],
+                b: pixels[offset + 2],
+            };
+            self.colors.insert(entity, color);
+            self.dirty.push(entity);
+        }
+    }
+
+    pub fn add_text(&mut self, entity: EntityId, text: Text) {
+        self.set_text(entity, text);
+    }
+
+    /// エンティティのラベルを更新し、`dirty` 集合に積む。
+    pub fn set_text(&mut self, entity: EntityId, text: Text) {
+        self.texts.insert(entity, text);
+        self.dirty.push(entity);
+    }
+
+    pub fn client_id(&self) -> u32 {
+        self.client_id
+    }
+
+    /// ローカルのLamportクロックをインクリメントし、新しい値を返す。
+    /// ローカルな操作（クリックなど）をスタンプする際に使う。
+    pub fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// リモートから受け取ったクロック値を観測し、ローカルのクロックを
+    /// `max(local, remote) + 1` に進める（Lamportクロックの同期則）。
+    pub fn observe(&mut self, remote_clock: u64) {
+        self.clock = self.clock.max(remote_clock) + 1;
+    }
+
+    /// `(version, client_id)` が記録済みの値より辞書式に新しい場合だけ色を
+    /// 適用し、適用したかどうかを返す。同点または古い更新、および存在しない
+    /// エンティティを指す更新（リモートからの不正な`entity`値）は無視される。
+    /// 未検証の`entity`をそのまま`set_color`に渡すとパックドピクセルバッファが
+    /// 任意のサイズへ伸長してしまうため、ここで一括して弾く。
+    pub fn apply_color_update(
+        &mut self,
+        entity: EntityId,
+        version: u64,
+        client_id: u32,
+        color: Color,
+    ) -> bool {
+        if entity >= self.next_entity_id {
+            return false;
+        }
+        let incoming = (version, client_id);
+        let is_newer = match self.last_version.get(&entity) {
+            Some(&stored) => incoming > stored,
+            None => true,
+        };
+        if is_newer {
+            self.last_version.insert(entity, incoming);
+            self.set_color(entity, color);
+        }
+        is_newer
+    }
+}
+
+/// `dirty` 集合に積まれたエンティティだけを再描画する。`Game::render_dirty`
+/// とWebSocketの`onmessage`ハンドラの両方から使われる共通の描画経路。
+fn draw_dirty_cells(context: &CanvasRenderingContext2d, world: &mut World) {
+    for entity in world.take_dirty() {
+        draw_cell(context, world, entity);
+    }
+}
+
+/// 1エンティティ分のマス目（矩形＋枠線）と、あれば`Text`ラベルを描画する。
+fn draw_cell(context: &CanvasRenderingContext2d, world: &World, entity: EntityId) {
+    if let (Some(pos), Some(size), Some(color)) = (
+        world.positions.get(entity),
+        world.sizes.get(entity),
+        world.pixel_color(entity),
+    ) {
+        context.set_fill_style(&JsValue::from_str(&format!(
+            "rgb({},{},{})",
+            color.r, color.g, color.b
+        )));
+        context.fill_rect(pos.x, pos.y, size.width, size.height);
+        context.set_stroke_style(&JsValue::from_str("black"));
+        context.stroke_rect(pos.x, pos.y, size.width, size.height);
+
+        if let Some(text) = world.texts.get(entity) {
+            let runs = convert_legacy(&text.raw);
+            draw_text_runs(context, &runs, pos.x + 4.0, pos.y + size.height / 2.0 + 4.0);
+        }
+    }
+}
+
+/// スタイル付きのランを左から右へ順に描画する。各ランの描画後、その
+/// 幅だけ`x`を進めて次のランに引き継ぐので、文字列の途中で色が
+/// 切り替わってもラベル全体が1行として並ぶ。
+fn draw_text_runs(context: &CanvasRenderingContext2d, runs: &[TextRun], mut x: f64, y: f64) {
+    for run in runs {
+        let weight = if run.bold { "bold" } else { "normal" };
+        let style = if run.italic { "italic" } else { "normal" };
+        context.set_font(&format!("{} {} 12px sans-serif", style, weight));
+        context.set_fill_style(&JsValue::from_str(run.color));
+        let _ = context.fill_text(&run.text, x, y);
+        if let Ok(metrics) = context.measure_text(&run.text) {
+            x += metrics.width();
+        }
+    }
+}
+
+// 他ピアが最後にホバーしていたマス目と、その最終更新時刻
+struct PeerCursor {
+    x: usize,
+    y: usize,
+    last_seen: f64,
+}
+
+// これより古いカーソルは切断済みとみなしてフェードアウトさせる
+const PEER_CURSOR_EXPIRY_MS: f64 = 5000.0;
+
+/// 期限切れのピアを取り除いた上で、残りのピアがホバーしているマス目に
+/// `client_id` から計算した色相の枠線を重ねて描画する。
+fn draw_peer_cursors(
+    context: &CanvasRenderingContext2d,
+    world: &World,
+    peers: &mut HashMap<u32, PeerCursor>,
+    grid_size: usize,
+    now: f64,
+) {
+    // 期限切れになったピアのセルは、枠線が残ったままにならないよう
+    // 通常のマス目として描き直してから取り除く
+    let expired: Vec<EntityId> = peers
+        .iter()
+        .filter(|(_, cursor)| now - cursor.last_seen >= PEER_CURSOR_EXPIRY_MS)
+        .map(|(_, cursor)| (cursor.y * grid_size + cursor.x) as EntityId)
+        .collect();
+    peers.retain(|_, cursor| now - cursor.last_seen < PEER_CURSOR_EXPIRY_MS);
+    for entity in expired {
+        draw_cell(context, world, entity);
+    }
+
+    for (&client_id, cursor) in peers.iter() {
+        let entity = (cursor.y * grid_size + cursor.x) as EntityId;
+        if let (Some(pos), Some(size)) = (world.positions.get(entity), world.sizes.get(entity)) {
+            let hue = client_id % 360;
+            context.set_stroke_style(&JsValue::from_str(&format!("hsl({}, 80%, 50%)", hue)));
+            context.set_line_width(3.0);
+            context.stroke_rect(pos.x + 1.5, pos.y + 1.5, size.width - 3.0, size.height - 3.0);
+            context.set_line_width(1.0);
+        }
     }
 }
 
@@ -105,6 +555,10 @@ pub struct Game {
     context: CanvasRenderingContext2d,
     ws: Option<WebSocket>,
     grid_size: usize,
+    // 他ピアのホバー位置（プレゼンス表示用）
+    peers: Rc<RefCell<HashMap<u32, PeerCursor>>>,
+    // 直前に配信したホバーセル。同じセルに留まっている間は再送しない。
+    last_hover: Option<(usize, usize)>,
 }
 
 #[wasm_bindgen]
@@ -122,6 +576,8 @@ impl Game {
             context,
             ws: None,
             grid_size: 8,
+            peers: Rc::new(RefCell::new(HashMap::new())),
+            last_hover: None,
         })
     }
 
@@ -131,51 +587,62 @@ impl Game {
 
         let world = Rc::clone(&self.world);
         let context = self.context.clone();
-        let canvas = self.canvas.clone();
+        let peers = Rc::clone(&self.peers);
+        let grid_size = self.grid_size;
 
         let onmessage_callback = Closure::wrap(Box::new(move |event: MessageEvent| {
-            if let Ok(text) = event.data().dyn_into::<js_sys::JsString>() {
-                if let Ok(message) = serde_json::from_str::<MessageType>(&text.as_string().unwrap()) {
-                    match message {
-                        MessageType::ColorUpdate { entity, r, g, b } => {
-                            // 色の更新を反映
-                            if let Ok(mut world) = world.try_borrow_mut() {
-                                if let Some(color) = world.colors.get_mut(entity) {
-                                    color.r = r;
-                                    color.g = g;
-                                    color.b = b;
-                                }
-                            }
-
-                            // キャンバスのクリア
-                            context.clear_rect(
-                                0.0,
-                                0.0,
-                                canvas.width() as f64,
-                                canvas.height() as f64,
-                            );
-
-                            // マス目の再描画
+            let data = event.data();
+            let message = if let Ok(buf) = data.clone().dyn_into::<js_sys::ArrayBuffer>() {
+                // バイナリフレーム（新形式のピア）
+                let bytes = js_sys::Uint8Array::new(&buf).to_vec();
+                MessageType::from_framed_bytes(&bytes)
+            } else if let Ok(text) = data.dyn_into::<js_sys::JsString>() {
+                // JSON文字列（古いピアとの互換性のため維持）
+                text.as_string().and_then(|s| serde_json::from_str::<MessageType>(&s).ok())
+            } else {
+                None
+            };
+
+            if let Some(message) = message {
+                match message {
+                    MessageType::ColorUpdate { entity, r, g, b, version, client_id } => {
+                        // Lamportクロックを同期し、LWWルールで色を反映してから、
+                        // 変わったセルだけを再描画する（全体クリア＆走査はしない）
+                        if let Ok(mut world) = world.try_borrow_mut() {
+                            world.observe(version);
+                            world.apply_color_update(entity, version, client_id, Color { r, g, b });
+                            draw_dirty_cells(&context, &mut world);
+                        }
+                    }
+                    MessageType::TextUpdate { entity, text } => {
+                        if let Ok(mut world) = world.try_borrow_mut() {
+                            world.set_text(entity, Text { raw: text });
+                            draw_dirty_cells(&context, &mut world);
+                        }
+                    }
+                    MessageType::Cursor { client_id, x, y } => {
+                        if let Ok(mut peers) = peers.try_borrow_mut() {
+                            let previous = peers.insert(client_id, PeerCursor { x, y, last_seen: js_sys::Date::now() });
                             if let Ok(world) = world.try_borrow() {
-                                for entity in 0..world.next_entity_id {
-                                    if let (Some(pos), Some(size), Some(color)) = (
-                                        world.positions.get(entity),
-                                        world.sizes.get(entity),
-                                        world.colors.get(entity),
-                                    ) {
-                                        context.set_fill_style(&JsValue::from_str(&format!(
-                                            "rgb({},{},{})",
-                                            color.r, color.g, color.b
-                                        )));
-                                        context.fill_rect(pos.x, pos.y, size.width, size.height);
-                                        context.set_stroke_style(&JsValue::from_str("black"));
-                                        context.stroke_rect(pos.x, pos.y, size.width, size.height);
+                                // 前回ホバーしていたセルに枠線が残らないよう、
+                                // 移動していれば先に通常のマス目として描き直す
+                                if let Some(prev) = previous {
+                                    if (prev.x, prev.y) != (x, y) {
+                                        let prev_entity = (prev.y * grid_size + prev.x) as EntityId;
+                                        draw_cell(&context, &world, prev_entity);
                                     }
                                 }
+                                draw_peer_cursors(&context, &world, &mut peers, grid_size, js_sys::Date::now());
                             }
                         }
-                        _ => {}
                     }
+                    MessageType::Snapshot { pixels } => {
+                        if let Ok(mut world) = world.try_borrow_mut() {
+                            world.apply_snapshot(&pixels);
+                            draw_dirty_cells(&context, &mut world);
+                        }
+                    }
+                    _ => {}
                 }
             }
         }) as Box<dyn FnMut(_)>);
@@ -231,22 +698,26 @@ impl Game {
         );
 
         // マス目の描画
-        let world = self.world.borrow();
+        let mut world = self.world.borrow_mut();
         for entity in 0..world.next_entity_id {
-            if let (Some(pos), Some(size), Some(color)) = (
-                world.positions.get(entity),
-                world.sizes.get(entity),
-                world.colors.get(entity),
-            ) {
-                self.context.set_fill_style(&JsValue::from_str(&format!(
-                    "rgb({},{},{})",
-                    color.r, color.g, color.b
-                )));
-                self.context.fill_rect(pos.x, pos.y, size.width, size.height);
-                self.context.set_stroke_style(&JsValue::from_str("black"));
-                self.context.stroke_rect(pos.x, pos.y, size.width, size.height);
-            }
+            draw_cell(&self.context, &world, entity);
         }
+        // 全体を描き切ったので、積み上がっていた差分は消化済みとして扱う
+        world.take_dirty();
+
+        let mut peers = self.peers.borrow_mut();
+        draw_peer_cursors(&self.context, &world, &mut peers, self.grid_size, js_sys::Date::now());
+    }
+
+    /// `dirty` 集合に積まれたセルだけを再描画する。単一セルの色変更のように
+    /// グリッド全体に影響しない更新では、`render` の全体クリア＆走査より
+    /// こちらを使うことで `grid_size` が大きくなってもレイテンシが一定に保てる。
+    pub fn render_dirty(&self) {
+        let mut world = self.world.borrow_mut();
+        draw_dirty_cells(&self.context, &mut world);
+
+        let mut peers = self.peers.borrow_mut();
+        draw_peer_cursors(&self.context, &world, &mut peers, self.grid_size, js_sys::Date::now());
     }
 
     pub fn handle_click(&mut self, event: MouseEvent) {
@@ -267,24 +738,66 @@ impl Game {
                     && y <= pos.y + size.height
                 {
                     // クリックされたマス目の色を変更
-                    if let Some(color) = world.colors.get_mut(entity) {
-                        color.r = 255 - color.r;
-                        color.g = 255 - color.g;
-                        color.b = 255 - color.b;
-
-                        // WebSocketで色の変更を送信
+                    if let Some(color) = world.colors.get(entity) {
+                        let new_color = Color {
+                            r: 255 - color.r,
+                            g: 255 - color.g,
+                            b: 255 - color.b,
+                        };
+
+                        // このクリックをLamportクロックでスタンプし、
+                        // ローカルのLWW記録にも反映しておく
+                        let version = world.tick();
+                        let client_id = world.client_id();
+                        world.apply_color_update(entity, version, client_id, new_color.clone());
+
+                        // WebSocketで色の変更を送信（バイナリフレーム）
                         if let Some(ws) = &self.ws {
                             let message = MessageType::ColorUpdate {
                                 entity,
-                                r: color.r,
-                                g: color.g,
-                                b: color.b,
+                                r: new_color.r,
+                                g: new_color.g,
+                                b: new_color.b,
+                                version,
+                                client_id,
                             };
-                            if let Ok(json) = serde_json::to_string(&message) {
-                                let _ = ws.send_with_str(&json);
-                            }
+                            let _ = ws.send_with_u8_array(&message.to_framed_bytes());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn handle_mousemove(&mut self, event: MouseEvent) {
+        let rect = self.canvas.get_bounding_client_rect();
+        let px = event.client_x() as f64 - rect.left();
+        let py = event.client_y() as f64 - rect.top();
+
+        let world = self.world.borrow();
+        // ホバーされたマス目を探し、前回とセルが変わっていれば
+        // 自分のカーソル位置を他ピアへ配信する（毎mousemoveでは送らない）
+        for entity in 0..world.next_entity_id {
+            if let (Some(pos), Some(size)) = (
+                world.positions.get(entity),
+                world.sizes.get(entity),
+            ) {
+                if px >= pos.x
+                    && px <= pos.x + size.width
+                    && py >= pos.y
+                    && py <= pos.y + size.height
+                {
+                    let x = entity as usize % self.grid_size;
+                    let y = entity as usize / self.grid_size;
+
+                    if self.last_hover != Some((x, y)) {
+                        self.last_hover = Some((x, y));
+                        if let Some(ws) = &self.ws {
+                            let message = MessageType::Cursor { client_id: world.client_id(), x, y };
+                            let _ = ws.send_with_u8_array(&message.to_framed_bytes());
                         }
                     }
+                    break;
                 }
             }
         }